@@ -1,3 +1,6 @@
+use clap::ValueEnum;
+use console::Term;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use opencv::core::no_array;
 use opencv::imgproc::{self, COLOR_BGR2RGB};
 use opencv::prelude::*;
@@ -5,10 +8,77 @@ use opencv::videoio::{
     VideoCapture, CAP_ANY, CAP_PROP_FPS, CAP_PROP_FRAME_COUNT, CAP_PROP_FRAME_HEIGHT,
     CAP_PROP_FRAME_WIDTH, CAP_PROP_POS_FRAMES,
 };
+use rand::Rng;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use tracing::{debug, trace};
+use std::io::Write;
+use std::time::Instant;
+use tracing::{debug, info, trace};
+
+/// How to average the pixels of a sampled frame into a single color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorAverage {
+    /// Average the gamma-encoded sRGB bytes directly (legacy behavior).
+    ///
+    /// Cheap, but biases the result towards mud since sRGB bytes aren't
+    /// linear in light intensity.
+    Naive,
+    /// Linearize each channel before averaging, then re-encode to sRGB.
+    Gamma,
+}
+
+/// How to decide which frames contribute a color to the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Sample exactly one frame per second.
+    Interval,
+    /// Sample one representative color per detected scene cut.
+    Scene,
+}
+
+/// How to turn a sampled frame's pixels into the color(s) reported for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Palette {
+    /// A single color averaged over every pixel in the frame.
+    Mean,
+    /// The dominant color(s), found by clustering the frame's pixels with k-means.
+    KMeans,
+}
+
+/// The color(s) reported for a single sampled frame — one for [`Palette::Mean`],
+/// up to `k` for [`Palette::KMeans`].
+pub type FrameColors = Vec<[u8; 3]>;
+
+/// Which file format to write the extracted colors in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A `{"colors": [...]}` JSON document.
+    Json,
+    /// A horizontal "movie barcode" image, one stripe per frame's first reported
+    /// color (the mean, or the largest k-means swatch).
+    Png,
+}
+
+struct Cluster {
+    centroid: [f64; 3],
+    count: u32,
+}
+
+#[derive(Debug, Default, Clone)]
+struct ShotAccumulator {
+    sum: [f64; 3],
+    count: u32,
+}
+
+/// The scene shots detected within a single chunk, plus the downscaled
+/// grayscale of its first/last frame so `extract_colors` can decide whether
+/// to stitch the chunk's boundary shots into its neighbors'.
+struct ChunkShots {
+    shots: Vec<ShotAccumulator>,
+    first_frame: Vec<u8>,
+    last_frame: Vec<u8>,
+}
 
 #[derive(Debug)]
 struct VideoStats {
@@ -20,7 +90,64 @@ struct VideoStats {
     height: i32,
 }
 
-pub fn extract_colors(input: &str) -> Result<Vec<[u8; 3]>, Box<dyn Error>> {
+/// The progress bars for a single chunk: its own bar plus a shared handle to
+/// the aggregate bar tracking every chunk's progress. Both are `None` when
+/// `--progress` wasn't requested or stderr isn't a TTY.
+#[derive(Clone)]
+struct Progress {
+    chunk_bar: Option<ProgressBar>,
+    overall_bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    fn tick(&self) {
+        if let Some(bar) = &self.chunk_bar {
+            bar.inc(1);
+        }
+        if let Some(bar) = &self.overall_bar {
+            bar.inc(1);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = &self.chunk_bar {
+            bar.finish();
+        }
+    }
+}
+
+/// Picks how many chunks to split a video into, so short clips don't spawn
+/// idle worker threads and long ones split evenly across what's available.
+fn determine_workers(
+    frame_count: i32,
+    fps: i32,
+    requested_workers: Option<usize>,
+    available_parallelism: usize,
+) -> usize {
+    if let Some(workers) = requested_workers {
+        return workers.max(1);
+    }
+
+    let available = available_parallelism.saturating_sub(1).max(1);
+
+    let min_chunk_size = std::cmp::max(1, fps * 90);
+    let useful_chunks =
+        std::cmp::max(1, (frame_count as f64 / min_chunk_size as f64).ceil() as usize);
+
+    std::cmp::min(available, useful_chunks)
+}
+
+pub fn extract_colors(
+    input: &str,
+    color_average: ColorAverage,
+    mode: Mode,
+    threshold: f64,
+    palette: Palette,
+    kmeans_k: usize,
+    all_swatches: bool,
+    workers: Option<usize>,
+    progress: bool,
+) -> Result<Vec<FrameColors>, Box<dyn Error>> {
     let video = VideoCapture::from_file(input, CAP_ANY)?;
     let stats = get_stats(&video)?;
 
@@ -29,11 +156,8 @@ pub fn extract_colors(input: &str) -> Result<Vec<[u8; 3]>, Box<dyn Error>> {
     let fps = stats.fps;
     let frame_count = stats.frame_count;
 
-    let min_chunk_size = fps * 90;
-    let number_of_chunks = std::cmp::min(
-        std::thread::available_parallelism().unwrap().get() - 1,
-        (frame_count as f64 / min_chunk_size as f64).ceil() as usize,
-    );
+    let available_parallelism = std::thread::available_parallelism()?.get();
+    let number_of_chunks = determine_workers(frame_count, fps, workers, available_parallelism);
 
     debug!(number_of_chunks);
 
@@ -45,17 +169,91 @@ pub fn extract_colors(input: &str) -> Result<Vec<[u8; 3]>, Box<dyn Error>> {
 
     debug!(chunks = ?(chunks.iter().map(|chunk| (chunk[0]..=chunk[chunk.len() - 1])).collect::<Vec<_>>()));
 
-    let colors = chunks
-        .par_iter()
-        .flat_map(|chunk| {
-            let mut video = VideoCapture::from_file(input, CAP_ANY).unwrap();
-            video.set(CAP_PROP_POS_FRAMES, chunk[0] as f64).unwrap();
+    let show_progress = progress && Term::stderr().is_term();
+    let multi_progress = show_progress.then(MultiProgress::new);
+
+    let overall_bar = multi_progress.as_ref().map(|mp| {
+        let bar = mp.add(ProgressBar::new(frame_count as u64));
+        bar.set_style(
+            ProgressStyle::with_template(
+                "overall [{bar:40.cyan/blue}] {pos}/{len} frames (eta {eta})",
+            )
+            .unwrap(),
+        );
+        bar
+    });
 
-            debug!(chunk = ?(chunk[0]..=chunk[chunk.len() - 1]));
+    let chunk_progress: Vec<Progress> = chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let chunk_bar = multi_progress.as_ref().map(|mp| {
+                let bar = mp.add(ProgressBar::new(chunk.len() as u64));
+                bar.set_style(
+                    ProgressStyle::with_template("chunk {msg} [{bar:40}] {pos}/{len}").unwrap(),
+                );
+                bar.set_message(index.to_string());
+                bar
+            });
 
-            get_colors(&mut video, &stats, chunk).unwrap()
+            Progress {
+                chunk_bar,
+                overall_bar: overall_bar.clone(),
+            }
         })
-        .collect::<Vec<_>>();
+        .collect();
+
+    let colors = match mode {
+        Mode::Interval => chunks
+            .par_iter()
+            .zip(chunk_progress.par_iter())
+            .flat_map(|(chunk, progress)| {
+                let mut video = VideoCapture::from_file(input, CAP_ANY).unwrap();
+                video.set(CAP_PROP_POS_FRAMES, chunk[0] as f64).unwrap();
+
+                debug!(chunk = ?(chunk[0]..=chunk[chunk.len() - 1]));
+
+                let colors = get_colors(
+                    &mut video,
+                    &stats,
+                    chunk,
+                    color_average,
+                    palette,
+                    kmeans_k,
+                    all_swatches,
+                    progress,
+                )
+                .unwrap();
+
+                progress.finish();
+
+                colors
+            })
+            .collect::<Vec<_>>(),
+        Mode::Scene => {
+            let chunk_shots = chunks
+                .par_iter()
+                .zip(chunk_progress.par_iter())
+                .map(|(chunk, progress)| {
+                    let mut video = VideoCapture::from_file(input, CAP_ANY).unwrap();
+                    video.set(CAP_PROP_POS_FRAMES, chunk[0] as f64).unwrap();
+
+                    debug!(chunk = ?(chunk[0]..=chunk[chunk.len() - 1]));
+
+                    let shots = get_colors_scene(&mut video, chunk, threshold, progress).unwrap();
+
+                    progress.finish();
+
+                    shots
+                })
+                .collect::<Vec<_>>();
+
+            stitch_shots(&chunk_shots, threshold)
+                .into_iter()
+                .map(|color| vec![color])
+                .collect()
+        }
+    };
 
     debug!(
         colors = format!(
@@ -81,6 +279,73 @@ pub fn extract_colors(input: &str) -> Result<Vec<[u8; 3]>, Box<dyn Error>> {
     Ok(colors)
 }
 
+/// Reads frames sequentially from stdin/a pipe, without seeking, and emits
+/// each sampled color immediately as a line of newline-delimited JSON on
+/// stdout. Used for `-` input, where the chunked parallel strategy of
+/// [`extract_colors`] isn't available because the source can't be seeked.
+pub fn extract_colors_streaming(
+    color_average: ColorAverage,
+    palette: Palette,
+    kmeans_k: usize,
+    all_swatches: bool,
+) -> Result<(), Box<dyn Error>> {
+    #[derive(Serialize)]
+    struct Line {
+        i: i32,
+        colors: FrameColors,
+    }
+
+    let mut video = VideoCapture::from_file("/dev/stdin", CAP_ANY)?;
+    let stats = get_stats(&video)?;
+
+    debug!(stats = ?stats);
+
+    let fps = std::cmp::max(1, stats.fps);
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    let started_at = Instant::now();
+    let mut frames_read = 0u64;
+    let mut i = 0;
+
+    loop {
+        let mut frame = Mat::default();
+        if !video.read(&mut frame)? {
+            break;
+        }
+
+        frames_read += 1;
+
+        if i % fps == 0 {
+            let frame_colors = match palette {
+                Palette::Mean => vec![get_mean_color(&frame, color_average)?],
+                Palette::KMeans => get_kmeans_colors(&frame, kmeans_k, all_swatches)?,
+            };
+
+            let line = Line {
+                i,
+                colors: frame_colors,
+            };
+
+            writeln!(out, "{}", serde_json::to_string(&line)?)?;
+            out.flush()?;
+        }
+
+        i += 1;
+
+        if frames_read % fps as u64 == 0 {
+            let throughput = frames_read as f64 / started_at.elapsed().as_secs_f64();
+            info!(throughput, unit = "frames/sec");
+        }
+    }
+
+    let throughput = frames_read as f64 / started_at.elapsed().as_secs_f64();
+    info!(frames_read, throughput, unit = "frames/sec", "done streaming");
+
+    Ok(())
+}
+
 fn get_stats(video: &VideoCapture) -> Result<VideoStats, Box<dyn Error>> {
     let fps = video.get(CAP_PROP_FPS)? as i32;
     let frame_count = video.get(CAP_PROP_FRAME_COUNT)? as i32;
@@ -99,7 +364,12 @@ fn get_colors(
     video: &mut VideoCapture,
     stats: &VideoStats,
     chunk: &[i32],
-) -> Result<Vec<[u8; 3]>, Box<dyn Error>> {
+    color_average: ColorAverage,
+    palette: Palette,
+    kmeans_k: usize,
+    all_swatches: bool,
+    progress: &Progress,
+) -> Result<Vec<FrameColors>, Box<dyn Error>> {
     let fps = stats.fps;
 
     let mut colors = vec![];
@@ -109,32 +379,385 @@ fn get_colors(
             let mut frame = Mat::default();
             video.read(&mut frame).unwrap();
 
-            let color = get_mean_color(&frame).unwrap();
+            let frame_colors = match palette {
+                Palette::Mean => vec![get_mean_color(&frame, color_average).unwrap()],
+                Palette::KMeans => get_kmeans_colors(&frame, kmeans_k, all_swatches).unwrap(),
+            };
 
-            trace!(i, ?color);
+            trace!(i, ?frame_colors);
 
-            colors.push(color);
+            colors.push(frame_colors);
         } else {
             video.grab().unwrap();
         }
+
+        progress.tick();
     });
 
     Ok(colors)
 }
 
-fn get_mean_color(frame: &Mat) -> Result<[u8; 3], Box<dyn Error>> {
+fn get_mean_color(frame: &Mat, color_average: ColorAverage) -> Result<[u8; 3], Box<dyn Error>> {
     let mut rgb_frame = Mat::default();
     imgproc::cvt_color(&frame, &mut rgb_frame, COLOR_BGR2RGB, 0).unwrap();
 
-    let mean = opencv::core::mean(&rgb_frame, &no_array()).unwrap();
+    match color_average {
+        ColorAverage::Naive => {
+            let mean = opencv::core::mean(&rgb_frame, &no_array()).unwrap();
+            Ok([mean[0] as u8, mean[1] as u8, mean[2] as u8])
+        }
+        ColorAverage::Gamma => {
+            let lin = linear_mean_of_frame(&rgb_frame).unwrap();
+            Ok([
+                linear_to_srgb(lin[0]),
+                linear_to_srgb(lin[1]),
+                linear_to_srgb(lin[2]),
+            ])
+        }
+    }
+}
+
+/// Averages the linear-light value of every pixel in an already RGB-converted frame.
+fn linear_mean_of_frame(rgb_frame: &Mat) -> Result<[f64; 3], Box<dyn Error>> {
+    let pixels = rgb_frame.data_bytes()?;
+    let mut sum = [0.0_f64; 3];
+    let mut count = 0.0_f64;
+
+    for px in pixels.chunks_exact(3) {
+        sum[0] += srgb_to_linear(px[0]);
+        sum[1] += srgb_to_linear(px[1]);
+        sum[2] += srgb_to_linear(px[2]);
+        count += 1.0;
+    }
+
+    Ok([sum[0] / count, sum[1] / count, sum[2] / count])
+}
+
+/// Finds the `k` dominant colors of a frame by clustering a sample of its
+/// pixels in RGB space, returning either just the largest cluster's centroid
+/// or all `k` centroids sorted largest-first.
+fn get_kmeans_colors(
+    frame: &Mat,
+    k: usize,
+    all_swatches: bool,
+) -> Result<FrameColors, Box<dyn Error>> {
+    const PIXEL_BUDGET: usize = 10_000;
+    const MAX_ITERATIONS: u32 = 20;
+    const CONVERGENCE_THRESHOLD: f64 = 1.0;
+
+    if k == 0 {
+        return Err("k-means requires at least one cluster (`k` must be >= 1)".into());
+    }
+
+    let mut rgb_frame = Mat::default();
+    imgproc::cvt_color(frame, &mut rgb_frame, COLOR_BGR2RGB, 0)?;
+
+    let pixels = sample_pixels(&rgb_frame, PIXEL_BUDGET)?;
+
+    let mut clusters = kmeans(&pixels, k, MAX_ITERATIONS, CONVERGENCE_THRESHOLD);
+    clusters.sort_by(|a, b| b.count.cmp(&a.count));
+
+    let swatches = if all_swatches {
+        clusters
+            .iter()
+            .map(|cluster| to_u8(cluster.centroid))
+            .collect()
+    } else {
+        vec![to_u8(clusters[0].centroid)]
+    };
+
+    Ok(swatches)
+}
+
+/// Takes an evenly-strided sample of a frame's pixels, down to roughly `budget` points.
+fn sample_pixels(rgb_frame: &Mat, budget: usize) -> Result<Vec<[f64; 3]>, Box<dyn Error>> {
+    let pixels = rgb_frame.data_bytes()?;
+    let total = pixels.len() / 3;
+    let stride = std::cmp::max(1, total / budget);
 
-    Ok([mean[0] as u8, mean[1] as u8, mean[2] as u8])
+    Ok(pixels
+        .chunks_exact(3)
+        .step_by(stride)
+        .map(|px| [px[0] as f64, px[1] as f64, px[2] as f64])
+        .collect())
 }
 
-pub fn write_colors_to_file(colors: &Vec<[u8; 3]>, path: &str) {
+/// Lloyd's algorithm with k-means++ seeding over 3-D RGB points.
+fn kmeans(
+    points: &[[f64; 3]],
+    k: usize,
+    max_iterations: u32,
+    convergence_threshold: f64,
+) -> Vec<Cluster> {
+    let mut rng = rand::thread_rng();
+    let mut centroids = kmeans_plus_plus_seed(points, k, &mut rng);
+
+    for _ in 0..max_iterations {
+        let mut sums = vec![[0.0_f64; 3]; centroids.len()];
+        let mut counts = vec![0u32; centroids.len()];
+
+        for point in points {
+            let nearest = nearest_centroid(point, &centroids);
+            sums[nearest][0] += point[0];
+            sums[nearest][1] += point[1];
+            sums[nearest][2] += point[2];
+            counts[nearest] += 1;
+        }
+
+        let mut shift = 0.0_f64;
+        for (i, centroid) in centroids.iter_mut().enumerate() {
+            if counts[i] == 0 {
+                continue;
+            }
+
+            let updated = [
+                sums[i][0] / counts[i] as f64,
+                sums[i][1] / counts[i] as f64,
+                sums[i][2] / counts[i] as f64,
+            ];
+
+            shift += distance(centroid, &updated);
+            *centroid = updated;
+        }
+
+        if shift < convergence_threshold {
+            break;
+        }
+    }
+
+    let mut counts = vec![0u32; centroids.len()];
+    for point in points {
+        counts[nearest_centroid(point, &centroids)] += 1;
+    }
+
+    centroids
+        .into_iter()
+        .zip(counts)
+        .map(|(centroid, count)| Cluster { centroid, count })
+        .collect()
+}
+
+/// Seeds `k` centroids with k-means++: the first chosen uniformly at random,
+/// each subsequent one with probability proportional to its squared distance
+/// from the nearest already-chosen centroid.
+fn kmeans_plus_plus_seed(points: &[[f64; 3]], k: usize, rng: &mut impl Rng) -> Vec<[f64; 3]> {
+    let mut centroids = vec![points[rng.gen_range(0..points.len())]];
+
+    while centroids.len() < k {
+        let weights: Vec<f64> = points
+            .iter()
+            .map(|point| nearest_distance_sq(point, &centroids))
+            .collect();
+
+        let total: f64 = weights.iter().sum();
+        let mut pick = rng.gen::<f64>() * total;
+
+        let next = points
+            .iter()
+            .zip(&weights)
+            .find(|(_, &weight)| {
+                pick -= weight;
+                pick <= 0.0
+            })
+            .map(|(point, _)| *point)
+            .unwrap_or(points[points.len() - 1]);
+
+        centroids.push(next);
+    }
+
+    centroids
+}
+
+fn nearest_centroid(point: &[f64; 3], centroids: &[[f64; 3]]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            distance_sq(point, a)
+                .partial_cmp(&distance_sq(point, b))
+                .unwrap()
+        })
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
+fn nearest_distance_sq(point: &[f64; 3], centroids: &[[f64; 3]]) -> f64 {
+    centroids
+        .iter()
+        .map(|centroid| distance_sq(point, centroid))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    distance_sq(a, b).sqrt()
+}
+
+fn distance_sq(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+fn to_u8(centroid: [f64; 3]) -> [u8; 3] {
+    [
+        centroid[0].round().clamp(0.0, 255.0) as u8,
+        centroid[1].round().clamp(0.0, 255.0) as u8,
+        centroid[2].round().clamp(0.0, 255.0) as u8,
+    ]
+}
+
+/// Walks every frame in `chunk` sequentially, flushing one [`ShotAccumulator`]
+/// per shot: a new shot starts at each detected scene cut, and the last shot
+/// is flushed when the chunk ends.
+///
+/// Scene detection is inherently sequential (each frame is compared against
+/// the one before it), so unlike [`get_colors`] this can't skip frames — it
+/// has to look at all of them to find the cuts.
+fn get_colors_scene(
+    video: &mut VideoCapture,
+    chunk: &[i32],
+    threshold: f64,
+    progress: &Progress,
+) -> Result<ChunkShots, Box<dyn Error>> {
+    let mut shots = vec![];
+    let mut current = ShotAccumulator::default();
+
+    let mut prev_gray: Option<Vec<u8>> = None;
+    let mut first_frame_gray: Option<Vec<u8>> = None;
+    let mut last_frame_gray = vec![];
+
+    for _ in chunk {
+        let mut frame = Mat::default();
+        video.read(&mut frame)?;
+
+        let gray = downscale_gray(&frame)?;
+
+        if first_frame_gray.is_none() {
+            first_frame_gray = Some(gray.clone());
+        }
+
+        if let Some(prev) = &prev_gray {
+            let diff = frame_diff(prev, &gray);
+
+            if diff > threshold {
+                trace!(diff, "scene cut detected");
+                shots.push(std::mem::take(&mut current));
+            }
+        }
+
+        let mut rgb_frame = Mat::default();
+        imgproc::cvt_color(&frame, &mut rgb_frame, COLOR_BGR2RGB, 0)?;
+        let lin = linear_mean_of_frame(&rgb_frame)?;
+
+        current.sum[0] += lin[0];
+        current.sum[1] += lin[1];
+        current.sum[2] += lin[2];
+        current.count += 1;
+
+        last_frame_gray = gray.clone();
+        prev_gray = Some(gray);
+
+        progress.tick();
+    }
+
+    shots.push(current);
+
+    Ok(ChunkShots {
+        shots,
+        first_frame: first_frame_gray.unwrap_or_default(),
+        last_frame: last_frame_gray,
+    })
+}
+
+/// Merges the last shot of each chunk into the first shot of the next one
+/// when the chunk boundary doesn't itself look like a scene cut, then
+/// finalizes every shot's running linear sum into an sRGB color.
+fn stitch_shots(chunk_shots: &[ChunkShots], threshold: f64) -> Vec<[u8; 3]> {
+    let mut merged: Vec<ShotAccumulator> = vec![];
+
+    for (i, chunk) in chunk_shots.iter().enumerate() {
+        for (j, shot) in chunk.shots.iter().enumerate() {
+            let straddles_boundary = j == 0
+                && i > 0
+                && frame_diff(&chunk_shots[i - 1].last_frame, &chunk.first_frame) <= threshold;
+
+            if straddles_boundary {
+                let prev = merged.last_mut().expect("previous chunk flushed a shot");
+                prev.sum[0] += shot.sum[0];
+                prev.sum[1] += shot.sum[1];
+                prev.sum[2] += shot.sum[2];
+                prev.count += shot.count;
+            } else {
+                merged.push(shot.clone());
+            }
+        }
+    }
+
+    merged
+        .iter()
+        .map(|shot| {
+            [
+                linear_to_srgb(shot.sum[0] / shot.count as f64),
+                linear_to_srgb(shot.sum[1] / shot.count as f64),
+                linear_to_srgb(shot.sum[2] / shot.count as f64),
+            ]
+        })
+        .collect()
+}
+
+/// Downscales a frame to a small fixed-size grayscale buffer for cheap
+/// frame-to-frame comparison.
+fn downscale_gray(frame: &Mat) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut small = Mat::default();
+    imgproc::resize(
+        frame,
+        &mut small,
+        opencv::core::Size::new(64, 36),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+
+    let mut gray = Mat::default();
+    imgproc::cvt_color(&small, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+
+    Ok(gray.data_bytes()?.to_vec())
+}
+
+/// Normalized sum of absolute differences between two equally-sized grayscale buffers.
+fn frame_diff(a: &[u8], b: &[u8]) -> f64 {
+    let sum: i64 = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (*x as i64 - *y as i64).abs())
+        .sum();
+
+    sum as f64 / (a.len() as f64 * 255.0)
+}
+
+/// Converts an 8-bit gamma-encoded sRGB channel value to linear light.
+fn srgb_to_linear(c: u8) -> f64 {
+    let s = c as f64 / 255.0;
+    if s <= 0.04045 {
+        s / 12.92
+    } else {
+        ((s + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear-light channel value back to an 8-bit gamma-encoded sRGB byte.
+fn linear_to_srgb(lin: f64) -> u8 {
+    let s = if lin <= 0.0031308 {
+        lin * 12.92
+    } else {
+        1.055 * lin.powf(1.0 / 2.4) - 0.055
+    };
+
+    (s.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub fn write_colors_to_file(colors: &Vec<FrameColors>, path: &str) {
     #[derive(Serialize, Deserialize)]
     struct Json {
-        colors: Vec<[u8; 3]>,
+        colors: Vec<FrameColors>,
     }
 
     let json = Json {
@@ -144,14 +767,222 @@ pub fn write_colors_to_file(colors: &Vec<[u8; 3]>, path: &str) {
     std::fs::write(path, serde_json::to_string(&json).unwrap()).unwrap();
 }
 
+/// Renders the colors as a horizontal barcode: one vertical stripe per frame,
+/// filled with its first reported color (the mean for [`Palette::Mean`], the
+/// largest cluster's centroid for [`Palette::KMeans`]), `height` pixels tall.
+pub fn write_colors_to_png(
+    colors: &Vec<FrameColors>,
+    path: &str,
+    height: u32,
+) -> Result<(), Box<dyn Error>> {
+    if colors.is_empty() {
+        return Err("no colors to export: input produced zero frames".into());
+    }
+
+    let width = colors.len() as u32;
+
+    let file = std::fs::File::create(path)?;
+    let writer = std::io::BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+
+    let mut buffer = vec![0u8; (width * height * 3) as usize];
+    for (x, frame_colors) in colors.iter().enumerate() {
+        let [r, g, b] = frame_colors[0];
+
+        for y in 0..height as usize {
+            let offset = (y * width as usize + x) * 3;
+            buffer[offset] = r;
+            buffer[offset + 1] = g;
+            buffer[offset + 2] = b;
+        }
+    }
+
+    writer.write_image_data(&buffer)?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_determine_workers_respects_explicit_override() {
+        assert_eq!(determine_workers(10_000, 30, Some(4), 8), 4);
+    }
+
+    #[test]
+    fn test_determine_workers_explicit_override_of_zero_is_at_least_one() {
+        assert_eq!(determine_workers(10_000, 30, Some(0), 8), 1);
+    }
+
+    #[test]
+    fn test_determine_workers_single_core_machine() {
+        assert_eq!(determine_workers(10_000, 30, None, 1), 1);
+    }
+
+    #[test]
+    fn test_determine_workers_short_clip_uses_one_chunk() {
+        let fps = 30;
+        let short_clip_frames = fps * 89;
+
+        assert_eq!(determine_workers(short_clip_frames, fps, None, 8), 1);
+    }
+
+    #[test]
+    fn test_determine_workers_long_clip_splits_across_available_cores() {
+        let fps = 30;
+        let long_clip_frames = fps * 90 * 10;
+
+        assert_eq!(determine_workers(long_clip_frames, fps, None, 8), 7);
+    }
+
     #[test]
     fn test_extract_colors() {
-        let colors = extract_colors("data/input.mp4").unwrap();
+        let colors = extract_colors(
+            "data/input.mp4",
+            ColorAverage::Gamma,
+            Mode::Interval,
+            0.3,
+            Palette::Mean,
+            5,
+            false,
+            None,
+            false,
+        )
+        .unwrap();
 
         assert_eq!(colors.len(), 10);
     }
+
+    #[test]
+    fn test_kmeans_separates_distinct_clusters() {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [255.0, 255.0, 255.0],
+            [254.0, 255.0, 255.0],
+            [255.0, 254.0, 255.0],
+        ];
+
+        let mut clusters = kmeans(&points, 2, 20, 1e-3);
+        clusters.sort_by(|a, b| a.centroid[0].partial_cmp(&b.centroid[0]).unwrap());
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].count, 3);
+        assert_eq!(clusters[1].count, 3);
+        assert!(clusters[0].centroid[0] < 10.0);
+        assert!(clusters[1].centroid[0] > 245.0);
+    }
+
+    #[test]
+    fn test_kmeans_plus_plus_seed_returns_k_distinct_centroids() {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [10.0, 10.0, 10.0],
+            [200.0, 200.0, 200.0],
+        ];
+
+        let mut rng = rand::thread_rng();
+        let centroids = kmeans_plus_plus_seed(&points, 3, &mut rng);
+
+        assert_eq!(centroids.len(), 3);
+        for point in &points {
+            assert!(centroids.contains(point));
+        }
+    }
+
+    #[test]
+    fn test_nearest_centroid_picks_closest() {
+        let centroids = [[0.0, 0.0, 0.0], [100.0, 100.0, 100.0]];
+
+        assert_eq!(nearest_centroid(&[5.0, 5.0, 5.0], &centroids), 0);
+        assert_eq!(nearest_centroid(&[95.0, 95.0, 95.0], &centroids), 1);
+    }
+    #[test]
+    fn test_frame_diff_identical_frames_is_zero() {
+        let frame = vec![100u8; 64 * 36];
+        assert_eq!(frame_diff(&frame, &frame), 0.0);
+    }
+
+    #[test]
+    fn test_frame_diff_opposite_frames_is_one() {
+        let black = vec![0u8; 4];
+        let white = vec![255u8; 4];
+        assert_eq!(frame_diff(&black, &white), 1.0);
+    }
+
+    #[test]
+    fn test_stitch_shots_merges_across_chunk_boundary() {
+        let chunk_a = ChunkShots {
+            shots: vec![ShotAccumulator {
+                sum: [10.0, 20.0, 30.0],
+                count: 2,
+            }],
+            first_frame: vec![0, 0, 0, 0],
+            last_frame: vec![10, 10, 10, 10],
+        };
+        let chunk_b = ChunkShots {
+            shots: vec![ShotAccumulator {
+                sum: [5.0, 5.0, 5.0],
+                count: 1,
+            }],
+            first_frame: vec![10, 10, 10, 10],
+            last_frame: vec![10, 10, 10, 10],
+        };
+
+        let shots = stitch_shots(&[chunk_a, chunk_b], 0.3);
+
+        assert_eq!(shots.len(), 1);
+        assert_eq!(
+            shots[0],
+            [
+                linear_to_srgb(15.0 / 3.0),
+                linear_to_srgb(25.0 / 3.0),
+                linear_to_srgb(35.0 / 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stitch_shots_keeps_real_cuts_separate() {
+        let chunk_a = ChunkShots {
+            shots: vec![ShotAccumulator {
+                sum: [10.0, 20.0, 30.0],
+                count: 2,
+            }],
+            first_frame: vec![0, 0, 0, 0],
+            last_frame: vec![0, 0, 0, 0],
+        };
+        let chunk_b = ChunkShots {
+            shots: vec![ShotAccumulator {
+                sum: [5.0, 5.0, 5.0],
+                count: 1,
+            }],
+            first_frame: vec![255, 255, 255, 255],
+            last_frame: vec![255, 255, 255, 255],
+        };
+
+        let shots = stitch_shots(&[chunk_a, chunk_b], 0.3);
+
+        assert_eq!(shots.len(), 2);
+        assert_eq!(
+            shots[0],
+            [
+                linear_to_srgb(5.0),
+                linear_to_srgb(10.0),
+                linear_to_srgb(15.0),
+            ]
+        );
+        assert_eq!(
+            shots[1],
+            [linear_to_srgb(5.0), linear_to_srgb(5.0), linear_to_srgb(5.0)]
+        );
+    }
 }
@@ -1,20 +1,70 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use std::error::Error;
 use std::path::PathBuf;
 use std::time::Instant;
 use tracing::{debug, info, Level};
-use video_colors::{extract_colors, write_colors_to_file};
+use video_colors::{
+    extract_colors, extract_colors_streaming, write_colors_to_file, write_colors_to_png,
+    ColorAverage, Mode, OutputFormat, Palette,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Input video file to operate on
+    /// Input video file to operate on, or `-` to read from stdin/a pipe
     input: PathBuf,
 
-    /// Optional output file, defaults to input file name with `.json` extension
+    /// Optional output file, defaults to the input file name with a `.json`
+    /// or `.png` extension matching `--format` (or `.json` if `--format` is
+    /// also omitted)
     #[arg(short, long, value_name = "FILE")]
     output: Option<PathBuf>,
 
+    /// How to average the pixels of a sampled frame into a single color
+    #[arg(short = 'a', long, value_enum, default_value = "gamma")]
+    color_average: ColorAverage,
+
+    /// How to decide which frames contribute a color to the output
+    ///
+    /// `scene` is not supported for streaming (`-`) input.
+    #[arg(short, long, value_enum, default_value = "interval")]
+    mode: Mode,
+
+    /// Normalized frame-difference threshold above which `--mode scene` declares a cut
+    #[arg(short, long, default_value_t = 0.3)]
+    threshold: f64,
+
+    /// How to turn a sampled frame's pixels into the color(s) reported for it
+    ///
+    /// `kmeans` is not supported together with `--mode scene`: a shot's color
+    /// is a running linear average, not a clustered sample.
+    #[arg(long, value_enum, default_value = "mean")]
+    palette: Palette,
+
+    /// Number of clusters to use for `--palette kmeans`
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(usize).range(1..))]
+    kmeans_k: usize,
+
+    /// Report all `--kmeans-k` swatches (sorted by cluster size) instead of just the dominant one
+    #[arg(long)]
+    all_swatches: bool,
+
+    /// Output format; inferred from the output file's extension if omitted
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Height in pixels of the barcode image written for `--format png`
+    #[arg(long, default_value_t = 120)]
+    height: u32,
+
+    /// Number of chunks to split the video into, overriding the automatic sizing
+    #[arg(short, long)]
+    workers: Option<usize>,
+
+    /// Show a progress bar per chunk plus an aggregate ETA (ignored when stderr isn't a TTY)
+    #[arg(long)]
+    progress: bool,
+
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
@@ -24,6 +74,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     let timer = Instant::now();
     let args = Cli::parse();
 
+    if args.mode == Mode::Scene && args.palette == Palette::KMeans {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--palette kmeans is not supported with --mode scene: a shot's color is a \
+                 running linear average, not a clustered sample",
+            )
+            .exit();
+    }
+
     tracing_subscriber::fmt()
         .compact()
         .with_file(true)
@@ -35,23 +95,75 @@ fn main() -> Result<(), Box<dyn Error>> {
             _ => Level::TRACE,
         })
         .with_thread_ids(true)
+        .with_writer(std::io::stderr)
         .init();
 
     info!("Extracting colors from {}", args.input.display());
 
     let input = args.input.into_os_string().into_string().unwrap();
+
+    if input == "-" {
+        if args.mode == Mode::Scene {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    "--mode scene is not supported for streaming (`-`) input; use --mode \
+                     interval instead",
+                )
+                .exit();
+        }
+
+        extract_colors_streaming(
+            args.color_average,
+            args.palette,
+            args.kmeans_k,
+            args.all_swatches,
+        )?;
+
+        info!("Done in {:.2?}", timer.elapsed());
+
+        return Ok(());
+    }
+
     let output = args
         .output
-        .unwrap_or_else(|| PathBuf::from(format!("{}.json", input)))
+        .unwrap_or_else(|| {
+            let ext = match args.format {
+                Some(OutputFormat::Png) => "png",
+                Some(OutputFormat::Json) | None => "json",
+            };
+            PathBuf::from(format!("{}.{}", input, ext))
+        })
         .into_os_string()
         .into_string()
         .unwrap();
 
     debug!(input, output, debug = args.debug);
 
-    let colors = extract_colors(&input)?;
+    let colors = extract_colors(
+        &input,
+        args.color_average,
+        args.mode,
+        args.threshold,
+        args.palette,
+        args.kmeans_k,
+        args.all_swatches,
+        args.workers,
+        args.progress,
+    )?;
+
+    let format = args.format.unwrap_or_else(|| {
+        if output.ends_with(".png") {
+            OutputFormat::Png
+        } else {
+            OutputFormat::Json
+        }
+    });
 
-    write_colors_to_file(&colors, &output);
+    match format {
+        OutputFormat::Json => write_colors_to_file(&colors, &output),
+        OutputFormat::Png => write_colors_to_png(&colors, &output, args.height)?,
+    }
 
     info!("Done in {:.2?}", timer.elapsed());
 